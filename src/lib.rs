@@ -1,25 +1,237 @@
 use std::error::Error;
 use std::fs;
 use std::env;
+use std::io::{self, BufRead, BufReader, IsTerminal};
+use std::path::Path;
 
-/// Searches for `query` in `contents` and returns a vector of lines
-/// that match, using case insensitive search if `config.ignore_case` is
-/// true. Prints each matching line and returns Ok(()) on success.
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.file_path)?;
+use regex::{Regex, RegexBuilder};
+
+/// ANSI escape sequence that opens a highlighted (bold red) span.
+const HIGHLIGHT: &str = "\x1b[1;31m";
+/// ANSI escape sequence that resets the terminal styling.
+const RESET: &str = "\x1b[0m";
+/// Files at or above this size are searched with a streaming `BufReader`
+/// rather than being read into memory all at once.
+const STREAM_THRESHOLD: u64 = 8 * 1024 * 1024;
 
-    let results = if config.ignore_case {
-        search_case_insensitive(&config.query, &contents)
+/// Searches each of `config.paths` for `query` and prints the matching
+/// lines, honouring the flags carried on `config`. When `config.invert` is
+/// set the non-matching lines are selected instead; `config.line_numbers`
+/// prefixes each printed line with its 1-based number; `config.count_only`
+/// prints just the number of matches. Case insensitive search is used when
+/// `config.ignore_case` is true.
+///
+/// When `config.recursive` is set a directory path is walked depth-first
+/// and every readable UTF-8 file beneath it is searched; directories that
+/// cannot be opened and files that are not valid UTF-8 are reported on
+/// stderr and skipped so one bad file does not abort the run. Matches from
+/// more than one file are prefixed with `path:` like grep.
+///
+/// Files at or above [`STREAM_THRESHOLD`] are read line-by-line through a
+/// `BufReader` so memory stays bounded regardless of file size. Returns
+/// Ok(()) on success.
+pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let regex = if config.use_regex {
+        let mut builder = RegexBuilder::new(&config.query);
+        builder.case_insensitive(config.ignore_case);
+        Some(builder.build()?)
+    } else {
+        None
+    };
+    let query = if config.ignore_case {
+        config.query.to_lowercase()
     } else {
-        search(&config.query, &contents)
+        config.query.clone()
+    };
+
+    let show_path = config.recursive || config.paths.len() > 1;
+    let colorize = match config.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => io::stdout().is_terminal(),
+    };
+    let render = Render {
+        regex: regex.as_ref(),
+        query: &query,
+        show_path,
+        colorize,
+    };
+    let mut count = 0;
+    for path in &config.paths {
+        let path = Path::new(path);
+        if config.recursive && path.is_dir() {
+            search_dir(&config, path, &render, &mut count);
+        } else {
+            // An explicitly named path keeps the original behaviour of
+            // propagating a read error rather than silently skipping it.
+            search_file(&config, path, &render, &mut count)?;
+        }
+    }
+
+    if config.count_only {
+        println!("{count}");
+    }
+    Ok(())
+}
+
+/// The slice of per-run render state that every file and line shares:
+/// the optional compiled regex, the (possibly lowercased) query, and
+/// whether to prefix matches with their path and colorize them. Bundling
+/// it keeps the search helpers to a handful of arguments.
+struct Render<'a> {
+    regex: Option<&'a Regex>,
+    query: &'a str,
+    show_path: bool,
+    colorize: bool,
+}
+
+/// Walks `dir` depth-first, searching every readable UTF-8 file beneath it.
+/// Directories that cannot be opened and files that cannot be read as UTF-8
+/// are reported on stderr and skipped so the traversal keeps going.
+fn search_dir(config: &Config, dir: &Path, render: &Render, count: &mut usize) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("{}: {err}", dir.display());
+            return;
+        }
     };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            search_dir(config, &path, render, count);
+        } else if let Err(err) = search_file(config, &path, render, count) {
+            eprintln!("{}: {err}", path.display());
+        }
+    }
+}
+
+/// Searches a single file, choosing a streaming `BufReader` for files at or
+/// above [`STREAM_THRESHOLD`] so memory stays bounded on huge inputs and
+/// reading the whole file into a `String` for everything else. Either way
+/// each line is handed to [`match_and_print`].
+fn search_file(
+    config: &Config,
+    path: &Path,
+    render: &Render,
+    count: &mut usize,
+) -> io::Result<()> {
+    let large = fs::metadata(path)
+        .map(|meta| meta.len() >= STREAM_THRESHOLD)
+        .unwrap_or(false);
 
-    for line in results {
-        println!("{line}");
+    if large {
+        let reader = BufReader::new(fs::File::open(path)?);
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            match_and_print(config, path, &line, number, render, count);
+        }
+    } else {
+        let contents = fs::read_to_string(path)?;
+        for (number, line) in contents.lines().enumerate() {
+            match_and_print(config, path, line, number, render, count);
+        }
     }
     Ok(())
 }
 
+/// Applies the match predicate to a single 0-based `number`ed `line` of
+/// `path` and, when it is selected (it matches, or with `config.invert` it
+/// does not), bumps `count` and prints it with the path and line-number
+/// prefixes requested by `config`. Shared by the in-memory
+/// and streaming read paths so both behave identically.
+fn match_and_print(
+    config: &Config,
+    path: &Path,
+    line: &str,
+    number: usize,
+    render: &Render,
+    count: &mut usize,
+) {
+    let is_match = match render.regex {
+        Some(regex) => !search_regex(regex, line).is_empty(),
+        None if config.ignore_case => line.to_lowercase().contains(render.query),
+        None => line.contains(render.query),
+    };
+    if is_match == config.invert {
+        return;
+    }
+    *count += 1;
+    if config.count_only {
+        return;
+    }
+    // Inverted matches contain no occurrence of the query, so there is
+    // nothing to highlight in them.
+    let rendered = if render.colorize && !config.invert {
+        highlight(line, render.query, config.ignore_case, render.regex)
+    } else {
+        line.to_string()
+    };
+    match (render.show_path, config.line_numbers) {
+        (true, true) => println!("{}:{}:{rendered}", path.display(), number + 1),
+        (true, false) => println!("{}:{rendered}", path.display()),
+        (false, true) => println!("{}:{rendered}", number + 1),
+        (false, false) => println!("{rendered}"),
+    }
+}
+
+/// Rebuilds `line` with every non-overlapping occurrence of the query
+/// wrapped in the [`HIGHLIGHT`]/[`RESET`] escape sequences. When a compiled
+/// `regex` is supplied its matches are highlighted; otherwise the plain
+/// `query` substring is highlighted, matched case-insensitively when
+/// `ignore_case` is set so matches are found regardless of case.
+fn highlight(line: &str, query: &str, ignore_case: bool, regex: Option<&Regex>) -> String {
+    let mut out = String::new();
+    let mut last = 0;
+
+    if let Some(regex) = regex {
+        for m in regex.find_iter(line) {
+            out.push_str(&line[last..m.start()]);
+            out.push_str(HIGHLIGHT);
+            out.push_str(m.as_str());
+            out.push_str(RESET);
+            last = m.end();
+        }
+    } else if query.is_empty() {
+        return line.to_string();
+    } else if ignore_case {
+        // `to_lowercase()` can change byte length (e.g. `İ`), so offsets
+        // taken from a lowercased copy cannot index back into `line`. Match
+        // case-insensitively over the original bytes with a literal regex so
+        // every span is a valid char boundary.
+        match RegexBuilder::new(&regex::escape(query))
+            .case_insensitive(true)
+            .build()
+        {
+            Ok(re) => {
+                for m in re.find_iter(line) {
+                    out.push_str(&line[last..m.start()]);
+                    out.push_str(HIGHLIGHT);
+                    out.push_str(m.as_str());
+                    out.push_str(RESET);
+                    last = m.end();
+                }
+            }
+            Err(_) => return line.to_string(),
+        }
+    } else {
+        let mut from = 0;
+        while let Some(pos) = line[from..].find(query) {
+            let start = from + pos;
+            let end = start + query.len();
+            out.push_str(&line[last..start]);
+            out.push_str(HIGHLIGHT);
+            out.push_str(&line[start..end]);
+            out.push_str(RESET);
+            last = end;
+            from = end;
+        }
+    }
+
+    out.push_str(&line[last..]);
+    out
+}
+
 /// Searches for `query` in `contents` case-insensitively and returns a
 /// vector of lines that contain `query`. Converts `query` and each line
 /// to lowercase before searching.
@@ -35,6 +247,17 @@ pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a st
     results
 }
 
+/// Searches `contents` for lines matched by the compiled regular
+/// expression `pattern` and returns a vector of references to those lines.
+/// The case sensitivity of the search is baked into `pattern` by the
+/// caller (see `RegexBuilder::case_insensitive`).
+pub fn search_regex<'a>(pattern: &Regex, contents: &'a str) -> Vec<&'a str> {
+    contents
+        .lines()
+        .filter(|line| pattern.is_match(line))
+        .collect()
+}
+
 /// Searches the given contents string for lines containing
 /// the given query string. Returns a vector of references
 /// to the matched lines. Performs a case-sensitive search.
@@ -45,43 +268,103 @@ pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
         .collect()
 }
 
+/// When colorized output should be emitted. `Auto` (the default) colours
+/// matches only when stdout is a terminal, mirroring grep's
+/// `--color=auto`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
 pub struct Config {
     pub query: String,
-    pub file_path: String,
+    pub paths: Vec<String>,
     pub ignore_case: bool,
+    pub invert: bool,
+    pub line_numbers: bool,
+    pub count_only: bool,
+    pub use_regex: bool,
+    pub recursive: bool,
+    pub color: ColorChoice,
 }
 /// Builds a Config from the given command line arguments.
 ///
-/// Parses the given arguments slice and returns a Config struct.
-/// Requires at least 3 arguments: the program name, the query string,
-/// and the file path. The ignore_case field is set based on the
-/// IGNORE_CASE environment variable.
+/// Parses the given arguments, separating grep-style flags from the
+/// positional query and file path: a token is treated as a flag when it
+/// begins with `-`, and the remaining tokens are collected in order as the
+/// query and then one or more file paths. Recognised flags are `-i` (force
+/// case-insensitive matching, overriding the IGNORE_CASE environment
+/// variable), `-v` (invert the match), `-n` (prefix each line with its
+/// 1-based number), `-c` (print only the count of matches), `-e` (treat the
+/// query as a regular expression) and `-r` (recurse into directories). The
+/// long flag `--color=<auto|always|never>` selects when matches are
+/// highlighted; a bare `--color` is treated as `auto`.
 ///
-/// Returns a Result with the Config or a static error string if there are
-/// not enough arguments.
+/// Returns a Result with the Config or a static error string if a flag is
+/// unknown or the query and at least one path are missing.
 impl Config {
     pub fn build(mut args: impl Iterator<Item = String>) -> Result<Config, &'static str> {
         // The first argument should be the name of the package
         args.next();
 
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => {
-                return Err("Didn't get a query string");
+        let mut ignore_case = env::var("IGNORE_CASE").is_ok();
+        let mut invert = false;
+        let mut line_numbers = false;
+        let mut count_only = false;
+        let mut use_regex = false;
+        let mut recursive = false;
+        let mut color = ColorChoice::Auto;
+        let mut positionals = Vec::new();
+
+        for arg in args {
+            if arg == "--color" {
+                color = ColorChoice::Auto;
+            } else if let Some(value) = arg.strip_prefix("--color=") {
+                color = match value {
+                    "auto" => ColorChoice::Auto,
+                    "always" => ColorChoice::Always,
+                    "never" => ColorChoice::Never,
+                    _ => return Err("Unknown color mode"),
+                };
+            } else if let Some(flag) = arg.strip_prefix('-') {
+                match flag {
+                    "i" => ignore_case = true,
+                    "v" => invert = true,
+                    "n" => line_numbers = true,
+                    "c" => count_only = true,
+                    "e" => use_regex = true,
+                    "r" => recursive = true,
+                    _ => return Err("Unknown flag"),
+                }
+            } else {
+                positionals.push(arg);
             }
-        };
-        let file_path = match args.next() {
+        }
+
+        let mut positionals = positionals.into_iter();
+        let query = match positionals.next() {
             Some(arg) => arg,
             None => {
-                return Err("Didn't get a file path");
+                return Err("Didn't get a query string");
             }
         };
-        let ignore_case = env::var("IGNORE_CASE").is_ok();
+        let paths: Vec<String> = positionals.collect();
+        if paths.is_empty() {
+            return Err("Didn't get a file path");
+        }
 
         Ok(Config {
             query,
-            file_path,
+            paths,
             ignore_case,
+            invert,
+            line_numbers,
+            count_only,
+            use_regex,
+            recursive,
+            color,
         })
     }
 }
@@ -122,4 +405,159 @@ Pick three.
 Trust me.";
         assert_eq!(vec!["Rust:", "Trust me."], search_case_insensitive(query, contents));
     }
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn build(args: &[&str]) -> Result<Config, &'static str> {
+        Config::build(args.iter().map(|s| s.to_string()))
+    }
+
+    /// A `Config` matching `query` case-sensitively with every flag off except
+    /// `count_only`, so matches are counted without printing each line.
+    fn plain_config(query: &str) -> Config {
+        Config {
+            query: query.to_string(),
+            paths: Vec::new(),
+            ignore_case: false,
+            invert: false,
+            line_numbers: false,
+            count_only: true,
+            use_regex: false,
+            recursive: false,
+            color: ColorChoice::Never,
+        }
+    }
+
+    /// Creates a unique temporary directory for a test to populate.
+    fn temp_dir() -> std::path::PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("minigrep-{}-{n}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Searches `contents` written to a temp file and returns the match count.
+    fn count_matches(config: &Config, contents: &str) -> usize {
+        let dir = temp_dir();
+        let file = dir.join("input.txt");
+        fs::write(&file, contents).unwrap();
+        let query = config.query.clone();
+        let render = Render {
+            regex: None,
+            query: &query,
+            show_path: false,
+            colorize: false,
+        };
+        let mut count = 0;
+        search_file(config, &file, &render, &mut count).unwrap();
+        fs::remove_dir_all(&dir).ok();
+        count
+    }
+
+    #[test]
+    fn build_parses_flags_and_positionals() {
+        let config = build(&["minigrep", "-i", "-v", "-n", "needle", "file.txt"]).unwrap();
+        assert_eq!("needle", config.query);
+        assert_eq!(vec!["file.txt".to_string()], config.paths);
+        assert!(config.ignore_case);
+        assert!(config.invert);
+        assert!(config.line_numbers);
+    }
+
+    #[test]
+    fn build_rejects_unknown_flag() {
+        assert_eq!("Unknown flag", build(&["minigrep", "-z", "q", "f"]).err().unwrap());
+    }
+
+    #[test]
+    fn build_requires_a_path() {
+        assert_eq!("Didn't get a file path", build(&["minigrep", "query"]).err().unwrap());
+    }
+
+    #[test]
+    fn streaming_path_matches_in_memory_path() {
+        // Pad the file past STREAM_THRESHOLD so `search_file` takes the
+        // BufReader branch, then confirm it finds the same matches.
+        let dir = temp_dir();
+        let file = dir.join("big.txt");
+        let filler = "no hit here\n".repeat(STREAM_THRESHOLD as usize / 12 + 1);
+        fs::write(&file, format!("{filler}needle\nneedle\n")).unwrap();
+        assert!(fs::metadata(&file).unwrap().len() >= STREAM_THRESHOLD);
+
+        let config = plain_config("needle");
+        let query = config.query.clone();
+        let render = Render {
+            regex: None,
+            query: &query,
+            show_path: false,
+            colorize: false,
+        };
+        let mut count = 0;
+        search_file(&config, &file, &render, &mut count).unwrap();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(2, count);
+    }
+
+    #[test]
+    fn recursive_walks_nested_directories() {
+        let dir = temp_dir();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "fast one\nslow\n").unwrap();
+        fs::write(dir.join("sub/b.txt"), "also fast\nfast again\n").unwrap();
+        fs::create_dir_all(dir.join("empty")).unwrap();
+
+        let config = plain_config("fast");
+        let query = config.query.clone();
+        let render = Render {
+            regex: None,
+            query: &query,
+            show_path: true,
+            colorize: false,
+        };
+        let mut count = 0;
+        search_dir(&config, &dir, &render, &mut count);
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(3, count);
+    }
+
+    #[test]
+    fn invert_selects_non_matching_lines() {
+        let contents = "\
+safe, fast, productive.
+Pick three.
+stay fast.";
+        let mut config = plain_config("fast");
+        assert_eq!(2, count_matches(&config, contents));
+        config.invert = true;
+        assert_eq!(1, count_matches(&config, contents));
+    }
+
+    #[test]
+    fn regex_word_boundary() {
+        let pattern = Regex::new(r"\bfast\b").unwrap();
+        let contents = "\
+safe, fast, productive.
+breakfast is ready.";
+        assert_eq!(vec!["safe, fast, productive."], search_regex(&pattern, contents));
+    }
+
+    #[test]
+    fn highlight_wraps_each_match() {
+        assert_eq!(
+            format!("a {HIGHLIGHT}fast{RESET} and {HIGHLIGHT}fast{RESET} cat"),
+            highlight("a fast and fast cat", "fast", false, None)
+        );
+    }
+
+    #[test]
+    fn highlight_non_ascii_case_insensitive() {
+        // `İ.to_lowercase()` is two code points, so lowercasing the line
+        // would desync byte offsets. The correct span must still land on
+        // `FAST` and must not panic on the trailing multi-byte `é`.
+        assert_eq!(
+            format!("İstanbul is {HIGHLIGHT}FAST{RESET}é"),
+            highlight("İstanbul is FASTé", "fast", true, None)
+        );
+    }
 }